@@ -1,49 +1,217 @@
 use anyhow::Result;
 use axum::{
     extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Path, Query, RawQuery, Request, State,
     },
-    response::{Html, IntoResponse},
+    http::{HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Json, Response},
     routing::get,
     Router,
 };
-use axum_server::bind;
+use axum_server::{bind, Handle};
 use chrono::{DateTime, Duration, TimeZone, Utc};
 use futures::StreamExt;
 use image::{DynamicImage, ImageFormat, RgbImage};
 use plotters::{prelude::*, style::full_palette::GREEN_800};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use starlink_grpc_client::client::DishClient;
 use starlink_grpc_client::space_x::api::device::response::Response as ResponseOneof;
-use std::{collections::VecDeque, fs, io::Cursor, net::SocketAddr, sync::Arc};
-use tokio::sync::{broadcast, Mutex};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    io::Cursor,
+    net::SocketAddr,
+    sync::Arc,
+};
+use tokio::sync::{broadcast, watch, Mutex};
+use tokio::task::JoinHandle;
+
+mod metrics;
+mod persistence;
+use metrics::{DishGauges, MetricsRegistry};
+use persistence::{Resolution, SeriesStore};
+
+/// How chart updates are put on the wire: server-rendered PNGs, or raw
+/// data points the browser renders itself.
+#[derive(Clone, Copy, Deserialize, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+enum RenderMode {
+    Png,
+    Data,
+}
+
+impl Default for RenderMode {
+    fn default() -> Self {
+        RenderMode::Png
+    }
+}
 
 // Type aliases to reduce complexity
 type DataPoint = (DateTime<Utc>, f64);
 type ChartHistory = VecDeque<DataPoint>;
+/// Identifies one configured dish (the key into `AppState::dishes`).
+type DishId = String;
 
 const INDEX_HTML: &str = include_str!("../static/index.html");
 
 #[derive(Clone)]
 struct AppState {
+    dishes: Arc<HashMap<DishId, DishState>>,
+    render_mode: RenderMode,
+    metrics: Arc<MetricsRegistry>,
+    auth_token: Option<Arc<str>>,
+}
+
+/// Everything one dish needs to serve `/initial/*`, `/ws` and `/history`:
+/// its own histories, broadcast channel and on-disk series store, all
+/// independent of every other configured dish.
+#[derive(Clone)]
+struct DishState {
     tx: broadcast::Sender<ChartMessage>,
     down_history: Arc<Mutex<ChartHistory>>,
     up_history: Arc<Mutex<ChartHistory>>,
     ping_history: Arc<Mutex<ChartHistory>>,
+    obstruction_history: Arc<Mutex<ChartHistory>>,
+    down_store: Arc<SeriesStore>,
+    up_store: Arc<SeriesStore>,
+    ping_store: Arc<SeriesStore>,
+    obstruction_store: Arc<SeriesStore>,
+    status: Arc<Mutex<DishStatusPanel>>,
+    gauges: DishGauges,
 }
 
 #[derive(Clone)]
 enum ChartMessage {
-    Downlink(Vec<u8>),
-    Uplink(Vec<u8>),
-    Ping(Vec<u8>),
+    Downlink(DishId, Vec<u8>),
+    Uplink(DishId, Vec<u8>),
+    Ping(DishId, Vec<u8>),
+    Obstruction(DishId, Vec<u8>),
+    Status(DishId, Vec<u8>),
+}
+
+/// Non-graph dish state pushed alongside the charts: obstruction flags, the
+/// alert bitfield, uptime and the running software version. Unlike the
+/// chart series this isn't retained on disk — only the latest snapshot
+/// matters, so it lives as a single value rather than a `ChartHistory`.
+///
+/// `alerts` packs the booleans `DishGetStatus.alerts` reports into a
+/// bitfield (bit 0 = motors_stuck, 1 = thermal_throttle, 2 =
+/// thermal_shutdown, 3 = mast_not_near_vertical, 4 = unexpected_location, 5
+/// = slow_ethernet_speeds, 6 = roaming, 7 = install_pending, 8 = is_heating,
+/// 9 = power_supply_thermal_throttle) so it travels as a single integer
+/// the same way the existing `starlink_dish_connected`-style gauges do.
+#[derive(Clone, Default, Serialize)]
+struct DishStatusPanel {
+    currently_obstructed: bool,
+    previously_obstructed: bool,
+    snr_above_noise_floor: bool,
+    alerts: u32,
+    uptime_s: u64,
+    software_version: String,
 }
 
 #[derive(Deserialize)]
 struct Config {
-    grpc_endpoint: String,
+    dishes: Vec<DishConfig>,
     history_capacity: usize,
+    #[serde(default)]
+    render_mode: RenderMode,
+    #[serde(default)]
+    persistence: PersistenceConfig,
+    /// Optional shared-secret gate for remote exposure. Checked as an
+    /// `Authorization: Bearer <token>` header, or a `?token=` query param
+    /// (the browser `WebSocket` constructor can't set custom headers, so
+    /// `/ws/:dish` needs this fallback). Unset by default, so localhost-only
+    /// use needs no configuration.
+    #[serde(default)]
+    auth_token: Option<String>,
+}
+
+/// One monitored dish: a stable `id` used in routes, metric labels and the
+/// per-dish data directory, plus the gRPC endpoint to stream status from.
+#[derive(Clone, Deserialize)]
+struct DishConfig {
+    id: String,
+    grpc_endpoint: String,
+}
+
+/// Retention/resolution knobs for the on-disk history in `persistence`.
+#[derive(Clone, Deserialize)]
+struct PersistenceConfig {
+    #[serde(default = "default_data_dir")]
+    data_dir: String,
+    /// How often the rollup/downsampling pass runs.
+    #[serde(default = "default_rollup_interval_secs")]
+    rollup_interval_secs: u64,
+    /// How long points stay at full 1 Hz resolution before graduating into
+    /// 1-minute buckets.
+    #[serde(default = "default_raw_retention_secs")]
+    raw_retention_secs: i64,
+    /// How long 1-minute buckets stick around before graduating into
+    /// 1-hour buckets.
+    #[serde(default = "default_minute_retention_secs")]
+    minute_retention_secs: i64,
+}
+
+impl Default for PersistenceConfig {
+    fn default() -> Self {
+        PersistenceConfig {
+            data_dir: default_data_dir(),
+            rollup_interval_secs: default_rollup_interval_secs(),
+            raw_retention_secs: default_raw_retention_secs(),
+            minute_retention_secs: default_minute_retention_secs(),
+        }
+    }
+}
+
+/// A dish id is length-prefixed with a single byte wherever it travels on
+/// the WebSocket wire (`push_dish_tag`), so anything longer can't be
+/// represented there.
+const MAX_DISH_ID_LEN: usize = u8::MAX as usize;
+
+/// Rejects a config with duplicate `dishes[].id` entries, or an id too long
+/// to fit `push_dish_tag`'s length prefix, up front: a `DishId` doubles as
+/// the key into `AppState::dishes`, the per-dish data directory and the
+/// `MetricsRegistry` gauge map (which silently collapse duplicates), and as
+/// the tag on every WebSocket frame for that dish (which silently truncates
+/// past `MAX_DISH_ID_LEN`), so this is the one place that needs to produce a
+/// clear error instead of letting ordinary config input panic or corrupt
+/// frames deeper in `main`.
+fn check_unique_dish_ids(dishes: &[DishConfig]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut seen = std::collections::HashSet::new();
+    for dish in dishes {
+        if !seen.insert(dish.id.as_str()) {
+            return Err(format!("duplicate dish id in config.toml: {:?}", dish.id).into());
+        }
+        if dish.id.len() > MAX_DISH_ID_LEN {
+            return Err(format!(
+                "dish id {:?} is {} bytes, longer than the {}-byte limit",
+                dish.id,
+                dish.id.len(),
+                MAX_DISH_ID_LEN
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+fn default_data_dir() -> String {
+    "data".to_string()
+}
+
+fn default_rollup_interval_secs() -> u64 {
+    60
+}
+
+fn default_raw_retention_secs() -> i64 {
+    3600
+}
+
+fn default_minute_retention_secs() -> i64 {
+    60 * 60 * 24 * 7
 }
 
 #[tokio::main]
@@ -51,89 +219,387 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Load config
     let cfg_str = fs::read_to_string("config.toml")?;
     let config: Config = toml::from_str(&cfg_str)?;
+    check_unique_dish_ids(&config.dishes)?;
 
-    // Shared state: channel + histories
-    let (tx, _) = broadcast::channel(16);
-    let down_history = Arc::new(Mutex::new(ChartHistory::new()));
-    let ping_history = Arc::new(Mutex::new(ChartHistory::new()));
-    let up_history = Arc::new(Mutex::new(ChartHistory::new()));
-    let state = AppState {
-        tx: tx.clone(),
-        down_history: down_history.clone(),
-        up_history: up_history.clone(),
-        ping_history: ping_history.clone(),
-    };
     let history_capacity = config.history_capacity;
+    let render_mode = config.render_mode;
+    let data_dir = std::path::Path::new(&config.persistence.data_dir);
 
-    // Pre-populate initial data for first load
-    {
-        let now = Utc::now();
-        let mut uh = up_history.lock().await;
-        let mut dh = down_history.lock().await;
-        let mut ph = ping_history.lock().await;
-        for n in 0..history_capacity {
-            uh.push_back((now - Duration::seconds((history_capacity - n) as i64), 0.0));
-            dh.push_back((now - Duration::seconds((history_capacity - n) as i64), 0.0));
-            ph.push_back((now - Duration::seconds((history_capacity - n) as i64), 25.0));
+    let dish_ids: Vec<String> = config.dishes.iter().map(|d| d.id.clone()).collect();
+    let (metrics_registry, mut gauges) = MetricsRegistry::new(&dish_ids);
+    let metrics_registry = Arc::new(metrics_registry);
+
+    // Shutdown signal: flipped by ctrl_c/SIGTERM, observed by every dish's
+    // reconnect loop and the HTTP server so all of them wind down instead
+    // of being killed mid-flight.
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(shutdown_signal(shutdown_tx.clone()));
+
+    let mut dishes = HashMap::new();
+    let mut dish_tasks: Vec<JoinHandle<()>> = Vec::new();
+
+    for dish in &config.dishes {
+        let (tx, _) = broadcast::channel(16);
+        let down_history = Arc::new(Mutex::new(ChartHistory::new()));
+        let up_history = Arc::new(Mutex::new(ChartHistory::new()));
+        let ping_history = Arc::new(Mutex::new(ChartHistory::new()));
+        let obstruction_history = Arc::new(Mutex::new(ChartHistory::new()));
+
+        let dish_dir = data_dir.join(&dish.id);
+        let down_store = Arc::new(SeriesStore::new(&dish_dir, "down"));
+        let up_store = Arc::new(SeriesStore::new(&dish_dir, "up"));
+        let ping_store = Arc::new(SeriesStore::new(&dish_dir, "ping"));
+        let obstruction_store = Arc::new(SeriesStore::new(&dish_dir, "obstruction"));
+
+        // Reload this dish's persisted history, falling back to the flat
+        // placeholder series so its charts aren't empty on a brand new
+        // install.
+        {
+            let mut dh = down_history.lock().await;
+            let mut uh = up_history.lock().await;
+            let mut ph = ping_history.lock().await;
+            let mut oh = obstruction_history.lock().await;
+            *dh = down_store.load_raw(history_capacity).await;
+            *uh = up_store.load_raw(history_capacity).await;
+            *ph = ping_store.load_raw(history_capacity).await;
+            *oh = obstruction_store.load_raw(history_capacity).await;
+            down_store.load_buckets().await;
+            up_store.load_buckets().await;
+            ping_store.load_buckets().await;
+            obstruction_store.load_buckets().await;
+
+            if dh.is_empty() && uh.is_empty() && ph.is_empty() && oh.is_empty() {
+                let now = Utc::now();
+                for n in 0..history_capacity {
+                    uh.push_back((now - Duration::seconds((history_capacity - n) as i64), 0.0));
+                    dh.push_back((now - Duration::seconds((history_capacity - n) as i64), 0.0));
+                    ph.push_back((now - Duration::seconds((history_capacity - n) as i64), 25.0));
+                    oh.push_back((now - Duration::seconds((history_capacity - n) as i64), 0.0));
+                }
+            }
+        }
+
+        // Periodically downsample this dish's aging raw points into
+        // 1-minute/1-hour buckets so long time windows stay queryable
+        // without the raw log growing without bound.
+        {
+            let down_store = down_store.clone();
+            let up_store = up_store.clone();
+            let ping_store = ping_store.clone();
+            let obstruction_store = obstruction_store.clone();
+            let raw_retention = Duration::seconds(config.persistence.raw_retention_secs);
+            let minute_retention = Duration::seconds(config.persistence.minute_retention_secs);
+            let rollup_interval =
+                std::time::Duration::from_secs(config.persistence.rollup_interval_secs);
+            let mut shutdown_rx = shutdown_rx.clone();
+
+            tokio::spawn(async move {
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep(rollup_interval) => {}
+                        _ = shutdown_rx.changed() => break,
+                    }
+                    down_store.roll_up(raw_retention, minute_retention).await;
+                    up_store.roll_up(raw_retention, minute_retention).await;
+                    ping_store.roll_up(raw_retention, minute_retention).await;
+                    obstruction_store
+                        .roll_up(raw_retention, minute_retention)
+                        .await;
+                }
+            });
         }
+
+        let gauges = gauges.remove(&dish.id).expect(
+            "check_unique_dish_ids rejected duplicates, so every id removed here is still present",
+        );
+
+        let state = DishState {
+            tx,
+            down_history,
+            up_history,
+            ping_history,
+            obstruction_history,
+            down_store,
+            up_store,
+            ping_store,
+            obstruction_store,
+            status: Arc::new(Mutex::new(DishStatusPanel::default())),
+            gauges,
+        };
+
+        dish_tasks.push(tokio::spawn(run_dish_stream(
+            dish.clone(),
+            state.clone(),
+            history_capacity,
+            render_mode,
+            shutdown_rx.clone(),
+        )));
+
+        dishes.insert(dish.id.clone(), state);
     }
 
-    // Spawn gRPC stream data generator
-    {
-        let down_history = down_history.clone();
-        let up_history = up_history.clone();
-        let tx = tx.clone();
-        let endpoint = config.grpc_endpoint.clone();
+    let state = AppState {
+        dishes: Arc::new(dishes),
+        render_mode,
+        metrics: metrics_registry,
+        auth_token: config.auth_token.map(Arc::from),
+    };
+
+    // Build routes. `/ws/:dish` checks the token itself (so it can reject
+    // with a close frame instead of an HTTP status once upgraded); every
+    // other route goes through the shared `require_token` gate.
+    let guarded_routes = Router::new()
+        .route("/", get(index))
+        .route("/dishes", get(list_dishes))
+        .route("/initial/:dish/down", get(initial_down))
+        .route("/initial/:dish/up", get(initial_up))
+        .route("/initial/:dish/ping", get(initial_ping))
+        .route("/initial/:dish/obstruction", get(initial_obstruction))
+        .route("/initial/:dish/status", get(initial_status))
+        .route("/history", get(history_handler))
+        .route("/metrics", get(metrics_handler))
+        .layer(middleware::from_fn_with_state(state.clone(), require_token));
 
+    let app = Router::new()
+        .route("/ws/:dish", get(ws_handler))
+        .merge(guarded_routes)
+        .with_state(state);
+
+    let addr: SocketAddr = "0.0.0.0:8080".parse().unwrap();
+    println!("Open your browser and navigate to http://localhost:8080");
+
+    let handle = Handle::new();
+    {
+        let handle = handle.clone();
+        let mut shutdown_rx = shutdown_rx.clone();
         tokio::spawn(async move {
-            loop {
-                eprintln!("Connecting to Dish endpoint: {}", endpoint);
-                match DishClient::connect(&endpoint).await {
-                    Ok(mut client) => {
-                        eprintln!("Connected to Dish endpoint");
-                        eprintln!("Opening gRPC status stream");
-                        match client.stream_status().await {
-                            Ok(mut stream) => {
-                                eprintln!("Status stream opened");
-                                while let Some(item) = stream.next().await {
-                                    // eprintln!("Waiting for the next message {}", Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
-                                    match item {
-                                        Ok(status) => {
-                                            if let Some(ResponseOneof::DishGetStatus(dgs)) =
-                                                status.raw.response
-                                            {
-                                                let down_val = dgs.downlink_throughput_bps as f64
-                                                    / 1_000_000.0;
-                                                let up_val =
-                                                    dgs.uplink_throughput_bps as f64 / 1_000_000.0;
-                                                let now = Utc::now();
-                                                // eprintln!("Received message: UP: {up_val:.2}, DOWN: {down_val:.2}");
-                                                // update histories
-                                                {
-                                                    let mut hist = down_history.lock().await;
-                                                    hist.push_back((now, down_val));
-                                                    if hist.len() > history_capacity {
-                                                        hist.pop_front();
+            let _ = shutdown_rx.changed().await;
+            handle.graceful_shutdown(Some(std::time::Duration::from_secs(10)));
+        });
+    }
+    bind(addr)
+        .handle(handle)
+        .serve(app.into_make_service())
+        .await?;
+
+    // Let every dish's reconnect loop observe the shutdown signal and exit
+    // cleanly instead of being aborted. Each point is already appended to
+    // its on-disk store as it arrives, so no history is pending here.
+    for task in dish_tasks {
+        let _ = task.await;
+    }
+    Ok(())
+}
+
+/// Waits for Ctrl+C or SIGTERM and flips the shared shutdown signal so every
+/// dish's reconnect loop and the HTTP server all wind down gracefully.
+async fn shutdown_signal(tx: watch::Sender<bool>) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    eprintln!("Shutdown signal received, winding down...");
+    let _ = tx.send(true);
+}
+
+/// Connects to one dish's gRPC endpoint and reconnects (with a backoff
+/// sleep) until `shutdown_rx` fires, pushing every status update into
+/// `state`'s histories, on-disk stores and gauges, and broadcasting it to
+/// that dish's `/ws` subscribers.
+async fn run_dish_stream(
+    dish: DishConfig,
+    state: DishState,
+    history_capacity: usize,
+    render_mode: RenderMode,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let DishState {
+        tx,
+        down_history,
+        up_history,
+        ping_history,
+        obstruction_history,
+        down_store,
+        up_store,
+        ping_store,
+        obstruction_store,
+        status,
+        gauges,
+    } = state;
+
+    while !*shutdown_rx.borrow() {
+        eprintln!(
+            "[{}] Connecting to Dish endpoint: {}",
+            dish.id, dish.grpc_endpoint
+        );
+        match DishClient::connect(&dish.grpc_endpoint).await {
+            Ok(mut client) => {
+                eprintln!("[{}] Connected to Dish endpoint", dish.id);
+                eprintln!("[{}] Opening gRPC status stream", dish.id);
+                match client.stream_status().await {
+                    Ok(mut stream) => {
+                        eprintln!("[{}] Status stream opened", dish.id);
+                        gauges.set_connected(true);
+                        loop {
+                            let item = tokio::select! {
+                                item = stream.next() => item,
+                                _ = shutdown_rx.changed() => {
+                                    eprintln!("[{}] Shutdown signal received, closing dish stream", dish.id);
+                                    break;
+                                }
+                            };
+                            let Some(item) = item else {
+                                break;
+                            };
+                            match item {
+                                Ok(status) => {
+                                    if let Some(ResponseOneof::DishGetStatus(dgs)) =
+                                        status.raw.response
+                                    {
+                                        let down_val =
+                                            dgs.downlink_throughput_bps as f64 / 1_000_000.0;
+                                        let up_val = dgs.uplink_throughput_bps as f64 / 1_000_000.0;
+                                        let now = Utc::now();
+                                        let ping_val = dgs.pop_ping_latency_ms as f64;
+                                        let obstruction_val = dgs
+                                            .obstruction_stats
+                                            .as_ref()
+                                            .map(|o| o.fraction_obstructed as f64 * 100.0)
+                                            .unwrap_or(0.0);
+
+                                        // update histories
+                                        {
+                                            let mut hist = down_history.lock().await;
+                                            hist.push_back((now, down_val));
+                                            if hist.len() > history_capacity {
+                                                hist.pop_front();
+                                            }
+                                        }
+                                        {
+                                            let mut hist = up_history.lock().await;
+                                            hist.push_back((now, up_val));
+                                            let mut phist = ping_history.lock().await;
+                                            phist.push_back((now, ping_val));
+                                            if phist.len() > history_capacity {
+                                                phist.pop_front();
+                                            }
+                                            if hist.len() > history_capacity {
+                                                hist.pop_front();
+                                            }
+                                        }
+                                        {
+                                            let mut ohist = obstruction_history.lock().await;
+                                            ohist.push_back((now, obstruction_val));
+                                            if ohist.len() > history_capacity {
+                                                ohist.pop_front();
+                                            }
+                                        }
+
+                                        let _ = down_store.append_raw((now, down_val)).await;
+                                        let _ = up_store.append_raw((now, up_val)).await;
+                                        let _ = ping_store.append_raw((now, ping_val)).await;
+                                        let _ = obstruction_store
+                                            .append_raw((now, obstruction_val))
+                                            .await;
+
+                                        gauges.set_downlink_mbps(down_val);
+                                        gauges.set_uplink_mbps(up_val);
+                                        gauges.set_ping_ms(ping_val);
+
+                                        // Diagnostic panel: obstruction flags, alert bitfield,
+                                        // uptime and software version, pushed alongside the
+                                        // charts rather than charted itself.
+                                        let panel = DishStatusPanel {
+                                            currently_obstructed: dgs
+                                                .obstruction_stats
+                                                .as_ref()
+                                                .map(|o| o.currently_obstructed)
+                                                .unwrap_or(false),
+                                            previously_obstructed: dgs
+                                                .obstruction_stats
+                                                .as_ref()
+                                                .map(|o| o.last_24h_obstructed_s > 0.0)
+                                                .unwrap_or(false),
+                                            snr_above_noise_floor: dgs.is_snr_above_noise_floor,
+                                            alerts: dgs
+                                                .alerts
+                                                .as_ref()
+                                                .map(|a| {
+                                                    let mut bits = 0u32;
+                                                    if a.motors_stuck {
+                                                        bits |= 1 << 0;
                                                     }
-                                                }
-                                                {
-                                                    let mut hist = up_history.lock().await;
-                                                    hist.push_back((now, up_val));
-                                                    let ping_val = dgs.pop_ping_latency_ms as f64;
-                                                    let mut phist = ping_history.lock().await;
-                                                    phist.push_back((now, ping_val));
-                                                    if phist.len() > history_capacity {
-                                                        phist.pop_front();
+                                                    if a.thermal_throttle {
+                                                        bits |= 1 << 1;
                                                     }
-                                                    if hist.len() > history_capacity {
-                                                        hist.pop_front();
+                                                    if a.thermal_shutdown {
+                                                        bits |= 1 << 2;
                                                     }
-                                                }
+                                                    if a.mast_not_near_vertical {
+                                                        bits |= 1 << 3;
+                                                    }
+                                                    if a.unexpected_location {
+                                                        bits |= 1 << 4;
+                                                    }
+                                                    if a.slow_ethernet_speeds {
+                                                        bits |= 1 << 5;
+                                                    }
+                                                    if a.roaming {
+                                                        bits |= 1 << 6;
+                                                    }
+                                                    if a.install_pending {
+                                                        bits |= 1 << 7;
+                                                    }
+                                                    if a.is_heating {
+                                                        bits |= 1 << 8;
+                                                    }
+                                                    if a.power_supply_thermal_throttle {
+                                                        bits |= 1 << 9;
+                                                    }
+                                                    bits
+                                                })
+                                                .unwrap_or(0),
+                                            uptime_s: dgs
+                                                .device_state
+                                                .as_ref()
+                                                .map(|s| s.uptime_s)
+                                                .unwrap_or(0),
+                                            software_version: dgs
+                                                .device_info
+                                                .as_ref()
+                                                .map(|i| i.software_version.clone())
+                                                .unwrap_or_default(),
+                                        };
+                                        *status.lock().await = panel.clone();
+                                        if let Ok(buf) = rmp_serde::to_vec(&panel) {
+                                            let _ =
+                                                tx.send(ChartMessage::Status(dish.id.clone(), buf));
+                                        }
 
-                                                // render and broadcast
+                                        // render and broadcast
+                                        match render_mode {
+                                            RenderMode::Png => {
                                                 let dh_vec = down_history.lock().await.clone();
                                                 let uh_vec = up_history.lock().await.clone();
                                                 let ph_vec = ping_history.lock().await.clone();
+                                                let oh_vec =
+                                                    obstruction_history.lock().await.clone();
 
                                                 if let Ok(buf) = render_png(
                                                     "Downlink Throughput",
@@ -141,7 +607,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                                     |v| v,
                                                     "Mbps",
                                                 ) {
-                                                    let _ = tx.send(ChartMessage::Downlink(buf));
+                                                    let _ = tx.send(ChartMessage::Downlink(
+                                                        dish.id.clone(),
+                                                        buf,
+                                                    ));
                                                 }
                                                 if let Ok(buf) = render_png(
                                                     "Uplink Throughput",
@@ -149,82 +618,347 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                                     |v| v,
                                                     "Mbps",
                                                 ) {
-                                                    let _ = tx.send(ChartMessage::Uplink(buf));
+                                                    let _ = tx.send(ChartMessage::Uplink(
+                                                        dish.id.clone(),
+                                                        buf,
+                                                    ));
                                                 }
                                                 if let Ok(buf) =
                                                     render_png("Ping Latency", &ph_vec, |v| v, "ms")
                                                 {
-                                                    let _ = tx.send(ChartMessage::Ping(buf));
+                                                    let _ = tx.send(ChartMessage::Ping(
+                                                        dish.id.clone(),
+                                                        buf,
+                                                    ));
+                                                }
+                                                if let Ok(buf) = render_png(
+                                                    "Obstruction",
+                                                    &oh_vec,
+                                                    |v| v,
+                                                    "% obstructed",
+                                                ) {
+                                                    let _ = tx.send(ChartMessage::Obstruction(
+                                                        dish.id.clone(),
+                                                        buf,
+                                                    ));
+                                                }
+                                            }
+                                            RenderMode::Data => {
+                                                if let Ok(buf) = encode_data_point((now, down_val))
+                                                {
+                                                    let _ = tx.send(ChartMessage::Downlink(
+                                                        dish.id.clone(),
+                                                        buf,
+                                                    ));
+                                                }
+                                                if let Ok(buf) = encode_data_point((now, up_val)) {
+                                                    let _ = tx.send(ChartMessage::Uplink(
+                                                        dish.id.clone(),
+                                                        buf,
+                                                    ));
+                                                }
+                                                if let Ok(buf) = encode_data_point((now, ping_val))
+                                                {
+                                                    let _ = tx.send(ChartMessage::Ping(
+                                                        dish.id.clone(),
+                                                        buf,
+                                                    ));
+                                                }
+                                                if let Ok(buf) =
+                                                    encode_data_point((now, obstruction_val))
+                                                {
+                                                    let _ = tx.send(ChartMessage::Obstruction(
+                                                        dish.id.clone(),
+                                                        buf,
+                                                    ));
                                                 }
                                             }
-                                        }
-                                        Err(err) => {
-                                            eprintln!("Stream error: {:?}", err);
                                         }
                                     }
                                 }
-                            }
-                            Err(err) => {
-                                eprintln!("Failed to open status stream: {:?}", err);
+                                Err(err) => {
+                                    eprintln!("[{}] Stream error: {:?}", dish.id, err);
+                                }
                             }
                         }
+                        gauges.set_connected(false);
                     }
                     Err(err) => {
-                        eprintln!("Connection error: {:?}", err);
+                        eprintln!("[{}] Failed to open status stream: {:?}", dish.id, err);
+                        gauges.set_connected(false);
                     }
                 }
-                eprintln!("Reconnecting to dish in 5 seconds...");
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
             }
-        });
+            Err(err) => {
+                eprintln!("[{}] Connection error: {:?}", dish.id, err);
+                gauges.set_connected(false);
+            }
+        }
+        if *shutdown_rx.borrow() {
+            break;
+        }
+        eprintln!("[{}] Reconnecting to dish in 5 seconds...", dish.id);
+        tokio::select! {
+            _ = tokio::time::sleep(std::time::Duration::from_secs(5)) => {}
+            _ = shutdown_rx.changed() => {}
+        }
     }
-    // Build routes
-    let app = Router::new()
-        .route("/", get(index))
-        .route("/ws", get(ws_handler))
-        .route("/initial/down", get(initial_down))
-        .route("/initial/up", get(initial_up))
-        .route("/initial/ping", get(initial_ping))
-        .with_state(state);
-
-    let addr: SocketAddr = "0.0.0.0:8080".parse().unwrap();
-    println!("Open your browser and navigate to http://localhost:8080");
-    bind(addr).serve(app.into_make_service()).await?;
-    Ok(())
+    eprintln!("[{}] gRPC reconnect loop exiting", dish.id);
 }
 
 async fn index() -> Html<&'static str> {
     Html(INDEX_HTML)
 }
 
-async fn initial_down(State(state): State<AppState>) -> impl IntoResponse {
-    let data = state.down_history.lock().await.clone();
-    if let Ok(png) = render_png("Downlink Throughput", &data, |v| v, "Mbps") {
-        ([("Content-Type", "image/png")], png)
+/// Lists the configured dish ids so the browser can offer a selector
+/// instead of hardcoding one dish per dashboard.
+async fn list_dishes(State(state): State<AppState>) -> impl IntoResponse {
+    let mut ids: Vec<&DishId> = state.dishes.keys().collect();
+    ids.sort();
+    Json(ids)
+}
+
+fn unknown_dish() -> Response {
+    (StatusCode::NOT_FOUND, "unknown dish".to_string()).into_response()
+}
+
+/// Gate applied to every route except `/ws/:dish` (which rejects with a
+/// close frame instead, since it can't return a plain HTTP status once
+/// upgraded). A no-op when `Config::auth_token` is unset, so plain
+/// localhost use is unaffected.
+async fn require_token(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let query = req.uri().query().map(str::to_owned);
+    if is_authorized(&state, req.headers(), query.as_deref()) {
+        next.run(req).await
     } else {
-        ([("Content-Type", "text/plain")], Vec::new())
+        (StatusCode::UNAUTHORIZED, "missing or invalid token").into_response()
     }
 }
-async fn initial_up(State(state): State<AppState>) -> impl IntoResponse {
-    let data = state.up_history.lock().await.clone();
-    if let Ok(png) = render_png("Uplink Throughput", &data, |v| v, "Mbps") {
-        ([("Content-Type", "image/png")], png)
-    } else {
-        ([("Content-Type", "text/plain")], Vec::new())
+
+/// Checks `headers`/`query` against `Config::auth_token`: an `Authorization:
+/// Bearer <token>` header, or a `token` query parameter as a fallback for
+/// the browser `WebSocket` client, which can't set custom headers.
+fn is_authorized(state: &AppState, headers: &HeaderMap, query: Option<&str>) -> bool {
+    let Some(expected) = &state.auth_token else {
+        return true;
+    };
+    let bearer_ok = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .is_some_and(|token| tokens_match(token, expected));
+    if bearer_ok {
+        return true;
     }
+    query
+        .and_then(token_from_query)
+        .is_some_and(|token| tokens_match(token, expected))
 }
 
-async fn initial_ping(State(state): State<AppState>) -> impl IntoResponse {
-    let data = state.ping_history.lock().await.clone();
-    if let Ok(png) = render_png("Ping Latency", &data, |v| v, "ms") {
-        ([("Content-Type", "image/png")], png)
-    } else {
-        ([("Content-Type", "text/plain")], Vec::new())
+/// Compares a presented token against the configured secret in constant
+/// time, so a client probing this internet-exposed port can't use response
+/// timing to recover `expected` one byte at a time.
+fn tokens_match(given: &str, expected: &str) -> bool {
+    let (given, expected) = (given.as_bytes(), expected.as_bytes());
+    if given.len() != expected.len() {
+        return false;
     }
+    let diff = given
+        .iter()
+        .zip(expected)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    diff == 0
+}
+
+fn token_from_query(query: &str) -> Option<&str> {
+    query.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == "token").then_some(value)
+    })
+}
+
+async fn initial_down(Path(dish): Path<String>, State(state): State<AppState>) -> Response {
+    let Some(dish_state) = state.dishes.get(&dish) else {
+        return unknown_dish();
+    };
+    let data = dish_state.down_history.lock().await.clone();
+    initial_response(state.render_mode, "Downlink Throughput", &data, "Mbps").into_response()
 }
 
-async fn ws_handler(ws: WebSocketUpgrade, State(state): State<AppState>) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_ws(socket, state.tx.clone()))
+async fn initial_up(Path(dish): Path<String>, State(state): State<AppState>) -> Response {
+    let Some(dish_state) = state.dishes.get(&dish) else {
+        return unknown_dish();
+    };
+    let data = dish_state.up_history.lock().await.clone();
+    initial_response(state.render_mode, "Uplink Throughput", &data, "Mbps").into_response()
+}
+
+async fn initial_ping(Path(dish): Path<String>, State(state): State<AppState>) -> Response {
+    let Some(dish_state) = state.dishes.get(&dish) else {
+        return unknown_dish();
+    };
+    let data = dish_state.ping_history.lock().await.clone();
+    initial_response(state.render_mode, "Ping Latency", &data, "ms").into_response()
+}
+
+async fn initial_obstruction(Path(dish): Path<String>, State(state): State<AppState>) -> Response {
+    let Some(dish_state) = state.dishes.get(&dish) else {
+        return unknown_dish();
+    };
+    let data = dish_state.obstruction_history.lock().await.clone();
+    initial_response(state.render_mode, "Obstruction", &data, "% obstructed").into_response()
+}
+
+/// `/initial/:dish/status` — the latest non-graph status panel, seeding the
+/// browser's alert/uptime display the same way `/initial/down` etc. seed
+/// the charts. Always JSON: unlike the charts this isn't affected by
+/// `RenderMode`.
+async fn initial_status(Path(dish): Path<String>, State(state): State<AppState>) -> Response {
+    let Some(dish_state) = state.dishes.get(&dish) else {
+        return unknown_dish();
+    };
+    let panel = dish_state.status.lock().await.clone();
+    Json(panel).into_response()
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    dish: String,
+    series: String,
+    from: Option<i64>,
+    to: Option<i64>,
+    resolution: Option<String>,
+}
+
+#[derive(Serialize)]
+struct HistoryPoint {
+    #[serde(rename = "t")]
+    start_ms: i64,
+    min: f64,
+    max: f64,
+    avg: f64,
+    count: u64,
+}
+
+/// `/history?dish=&series=down&from=&to=&resolution=` — queries the
+/// downsampled, disk-backed series store for one dish so a user can ask for
+/// windows (e.g. the last 24 hours) far beyond what the in-memory
+/// `ChartHistory` keeps for the live chart. `resolution` is one of `raw`,
+/// `minute`, `hour` (default `raw`).
+async fn history_handler(
+    State(state): State<AppState>,
+    Query(q): Query<HistoryQuery>,
+) -> impl IntoResponse {
+    let Some(dish_state) = state.dishes.get(&q.dish) else {
+        return Err((StatusCode::NOT_FOUND, "unknown dish".to_string()));
+    };
+    let store = match q.series.as_str() {
+        "down" => &dish_state.down_store,
+        "up" => &dish_state.up_store,
+        "ping" => &dish_state.ping_store,
+        "obstruction" => &dish_state.obstruction_store,
+        _ => return Err((StatusCode::BAD_REQUEST, "unknown series".to_string())),
+    };
+    let resolution = match q.resolution.as_deref() {
+        None => Resolution::Raw,
+        Some(s) => match Resolution::parse(s) {
+            Some(r) => r,
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    "resolution must be raw, minute, or hour".to_string(),
+                ))
+            }
+        },
+    };
+    let to =
+        q.to.and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+            .unwrap_or_else(Utc::now);
+    let from = q
+        .from
+        .and_then(|ms| Utc.timestamp_millis_opt(ms).single())
+        .unwrap_or(to - Duration::hours(24));
+
+    let points: Vec<HistoryPoint> = store
+        .query(from, to, resolution)
+        .await
+        .into_iter()
+        .map(|b| HistoryPoint {
+            start_ms: b.start.timestamp_millis(),
+            min: b.min,
+            max: b.max,
+            avg: b.avg,
+            count: b.count,
+        })
+        .collect();
+    Ok(Json(points))
+}
+
+async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [("Content-Type", "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+}
+
+/// Render a history buffer for one of the `/initial/*` routes, honoring the
+/// configured `RenderMode` so a freshly-connected browser can seed its
+/// buffers the same way it's kept up to date over `/ws`.
+fn initial_response(
+    mode: RenderMode,
+    title: &str,
+    data: &ChartHistory,
+    y_desc: &str,
+) -> ([(&'static str, &'static str); 1], Vec<u8>) {
+    match mode {
+        RenderMode::Png => {
+            if let Ok(png) = render_png(title, data, |v| v, y_desc) {
+                ([("Content-Type", "image/png")], png)
+            } else {
+                ([("Content-Type", "text/plain")], Vec::new())
+            }
+        }
+        RenderMode::Data => {
+            if let Ok(buf) = encode_data_series(data) {
+                ([("Content-Type", "application/msgpack")], buf)
+            } else {
+                ([("Content-Type", "text/plain")], Vec::new())
+            }
+        }
+    }
+}
+
+async fn ws_handler(
+    Path(dish): Path<String>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+    State(state): State<AppState>,
+) -> Response {
+    // Checked before the dish lookup: an unauthenticated client must see the
+    // same close frame whether or not `dish` exists, so this route can't be
+    // used to enumerate configured dish ids.
+    if !is_authorized(&state, &headers, query.as_deref()) {
+        return ws.on_upgrade(reject_unauthorized).into_response();
+    }
+    let Some(dish_state) = state.dishes.get(&dish) else {
+        return unknown_dish();
+    };
+    let tx = dish_state.tx.clone();
+    ws.on_upgrade(move |socket| handle_ws(socket, tx))
+        .into_response()
+}
+
+/// Completes the upgrade (browsers give no way to reject it earlier) and
+/// immediately closes with a close frame instead of streaming any data, so
+/// an unauthenticated client never gets a live connection.
+async fn reject_unauthorized(mut socket: WebSocket) {
+    let _ = socket
+        .send(Message::Close(Some(CloseFrame {
+            code: 4401,
+            reason: "unauthorized".into(),
+        })))
+        .await;
 }
 
 async fn handle_ws(mut socket: WebSocket, tx: broadcast::Sender<ChartMessage>) {
@@ -232,16 +966,29 @@ async fn handle_ws(mut socket: WebSocket, tx: broadcast::Sender<ChartMessage>) {
     while let Ok(msg) = rx.recv().await {
         let mut data = Vec::new();
         match msg {
-            ChartMessage::Downlink(buf) => {
+            ChartMessage::Downlink(dish, buf) => {
                 data.push(0);
+                push_dish_tag(&mut data, &dish);
                 data.extend(buf);
             }
-            ChartMessage::Uplink(buf) => {
+            ChartMessage::Uplink(dish, buf) => {
                 data.push(1);
+                push_dish_tag(&mut data, &dish);
                 data.extend(buf);
             }
-            ChartMessage::Ping(buf) => {
+            ChartMessage::Ping(dish, buf) => {
                 data.push(2);
+                push_dish_tag(&mut data, &dish);
+                data.extend(buf);
+            }
+            ChartMessage::Obstruction(dish, buf) => {
+                data.push(3);
+                push_dish_tag(&mut data, &dish);
+                data.extend(buf);
+            }
+            ChartMessage::Status(dish, buf) => {
+                data.push(4);
+                push_dish_tag(&mut data, &dish);
                 data.extend(buf);
             }
         }
@@ -251,6 +998,36 @@ async fn handle_ws(mut socket: WebSocket, tx: broadcast::Sender<ChartMessage>) {
     }
 }
 
+/// Appends a length-prefixed dish id to a WebSocket frame, right after the
+/// series tag byte, so a client subscribed to `/ws/:dish` can still tell
+/// which dish a frame belongs to (and a future multi-dish view could
+/// multiplex several dishes over one connection). The length prefix is a
+/// single byte, so `dish` must be at most `MAX_DISH_ID_LEN` bytes;
+/// `check_unique_dish_ids` enforces that on every configured id at startup.
+fn push_dish_tag(data: &mut Vec<u8>, dish: &str) {
+    let bytes = dish.as_bytes();
+    data.push(bytes.len() as u8);
+    data.extend_from_slice(bytes);
+}
+
+/// Serialize a single new `DataPoint` for the `RenderMode::Data` wire mode.
+/// The series tag byte and dish id are added later by `handle_ws`,
+/// matching how PNG payloads are tagged.
+fn encode_data_point(point: DataPoint) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    Ok(rmp_serde::to_vec(&(point.0.timestamp_millis(), point.1))?)
+}
+
+/// Serialize a full history buffer for `/initial/*` in `RenderMode::Data`,
+/// so the browser can seed its chart before switching to incremental
+/// per-point updates over `/ws`.
+fn encode_data_series(data: &ChartHistory) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let points: Vec<(i64, f64)> = data
+        .iter()
+        .map(|(t, v)| (t.timestamp_millis(), *v))
+        .collect();
+    Ok(rmp_serde::to_vec(&points)?)
+}
+
 fn render_png<F>(
     title: &str,
     data: &ChartHistory,
@@ -315,3 +1092,112 @@ where
     dyn_img.write_to(&mut cursor, ImageFormat::Png)?;
     Ok(cursor.into_inner())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::header::AUTHORIZATION;
+    use axum::http::HeaderValue;
+
+    fn state_with_token(token: &str) -> AppState {
+        let (registry, _gauges) = MetricsRegistry::new(&[]);
+        AppState {
+            dishes: Arc::new(HashMap::new()),
+            render_mode: RenderMode::Png,
+            metrics: Arc::new(registry),
+            auth_token: Some(Arc::from(token)),
+        }
+    }
+
+    #[test]
+    fn token_from_query_finds_token_param() {
+        assert_eq!(token_from_query("token=abc123"), Some("abc123"));
+        assert_eq!(token_from_query("dish=1&token=abc123"), Some("abc123"));
+        assert_eq!(token_from_query("token=abc123&dish=1"), Some("abc123"));
+    }
+
+    #[test]
+    fn token_from_query_missing_returns_none() {
+        assert_eq!(token_from_query("dish=1"), None);
+        assert_eq!(token_from_query(""), None);
+    }
+
+    #[test]
+    fn is_authorized_allows_everything_when_no_token_configured() {
+        let (registry, _gauges) = MetricsRegistry::new(&[]);
+        let state = AppState {
+            dishes: Arc::new(HashMap::new()),
+            render_mode: RenderMode::Png,
+            metrics: Arc::new(registry),
+            auth_token: None,
+        };
+        assert!(is_authorized(&state, &HeaderMap::new(), None));
+    }
+
+    #[test]
+    fn is_authorized_accepts_matching_bearer_header() {
+        let state = state_with_token("secret");
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        assert!(is_authorized(&state, &headers, None));
+    }
+
+    #[test]
+    fn is_authorized_accepts_matching_query_token() {
+        let state = state_with_token("secret");
+        assert!(is_authorized(
+            &state,
+            &HeaderMap::new(),
+            Some("token=secret")
+        ));
+    }
+
+    #[test]
+    fn is_authorized_rejects_wrong_or_missing_token() {
+        let state = state_with_token("secret");
+        assert!(!is_authorized(&state, &HeaderMap::new(), None));
+        assert!(!is_authorized(
+            &state,
+            &HeaderMap::new(),
+            Some("token=wrong")
+        ));
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer wrong"));
+        assert!(!is_authorized(&state, &headers, None));
+    }
+
+    #[test]
+    fn tokens_match_compares_equal_and_unequal_tokens() {
+        assert!(tokens_match("secret", "secret"));
+        assert!(!tokens_match("secret", "wrong!"));
+        assert!(!tokens_match("secret", "secretbutlonger"));
+        assert!(!tokens_match("", "secret"));
+    }
+
+    fn dish(id: &str) -> DishConfig {
+        DishConfig {
+            id: id.to_string(),
+            grpc_endpoint: "http://127.0.0.1:9200".to_string(),
+        }
+    }
+
+    #[test]
+    fn check_unique_dish_ids_allows_distinct_ids() {
+        assert!(check_unique_dish_ids(&[dish("a"), dish("b")]).is_ok());
+    }
+
+    #[test]
+    fn check_unique_dish_ids_rejects_duplicates() {
+        assert!(check_unique_dish_ids(&[dish("a"), dish("b"), dish("a")]).is_err());
+    }
+
+    #[test]
+    fn check_unique_dish_ids_allows_max_length_id() {
+        assert!(check_unique_dish_ids(&[dish(&"a".repeat(MAX_DISH_ID_LEN))]).is_ok());
+    }
+
+    #[test]
+    fn check_unique_dish_ids_rejects_id_over_max_length() {
+        assert!(check_unique_dish_ids(&[dish(&"a".repeat(MAX_DISH_ID_LEN + 1))]).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! Prometheus metrics for the live dish stream(s), exposed at `/metrics` via
+//! `opentelemetry-prometheus` so the dashboard can be scraped into existing
+//! monitoring instead of screen-scraping the PNG charts. All dishes share
+//! one registry; series are distinguished by a `dish` attribute.
+
+use opentelemetry::metrics::MeterProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use prometheus::{Encoder, Registry, TextEncoder};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Live gauge values for one dish, pushed from its gRPC loop whenever a
+/// status message updates the histories. The observable-gauge callbacks
+/// registered in `MetricsRegistry::new` read these back lazily whenever
+/// `/metrics` is scraped.
+#[derive(Clone)]
+pub struct DishGauges {
+    downlink_mbps: Arc<AtomicU64>,
+    uplink_mbps: Arc<AtomicU64>,
+    ping_ms: Arc<AtomicU64>,
+    connected: Arc<AtomicU64>,
+}
+
+impl DishGauges {
+    fn new() -> Self {
+        DishGauges {
+            downlink_mbps: Arc::new(AtomicU64::new(0f64.to_bits())),
+            uplink_mbps: Arc::new(AtomicU64::new(0f64.to_bits())),
+            ping_ms: Arc::new(AtomicU64::new(0f64.to_bits())),
+            connected: Arc::new(AtomicU64::new(0f64.to_bits())),
+        }
+    }
+
+    pub fn set_downlink_mbps(&self, value: f64) {
+        self.downlink_mbps.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_uplink_mbps(&self, value: f64) {
+        self.uplink_mbps.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_ping_ms(&self, value: f64) {
+        self.ping_ms.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_connected(&self, connected: bool) {
+        let value = if connected { 1.0 } else { 0.0 };
+        self.connected.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// The process-wide Prometheus registry backing `/metrics`.
+pub struct MetricsRegistry {
+    registry: Registry,
+}
+
+impl MetricsRegistry {
+    /// Builds the shared registry and one set of gauges per dish id. The
+    /// dish id set is fixed at startup (from `Config`), so every gauge
+    /// callback can be registered once here and simply iterate the map on
+    /// each scrape.
+    pub fn new(dish_ids: &[String]) -> (Self, HashMap<String, DishGauges>) {
+        let registry = Registry::new();
+        let exporter = opentelemetry_prometheus::exporter()
+            .with_registry(registry.clone())
+            .build()
+            .expect("failed to build Prometheus exporter");
+        let provider = SdkMeterProvider::builder().with_reader(exporter).build();
+        let meter = provider.meter("starlink_web_dashboard");
+
+        let gauges: HashMap<String, DishGauges> = dish_ids
+            .iter()
+            .map(|id| (id.clone(), DishGauges::new()))
+            .collect();
+        // Each `DishGauges`' fields are themselves `Arc`s, so this clone of
+        // the map is cheap and the callbacks below observe the same atomics
+        // `gauges` is returned with below.
+        let shared = Arc::new(gauges.clone());
+
+        register_gauge(
+            &meter,
+            "starlink_downlink_mbps",
+            "Downlink throughput reported by each dish, in Mbps",
+            shared.clone(),
+            |g| f64::from_bits(g.downlink_mbps.load(Ordering::Relaxed)),
+        );
+        register_gauge(
+            &meter,
+            "starlink_uplink_mbps",
+            "Uplink throughput reported by each dish, in Mbps",
+            shared.clone(),
+            |g| f64::from_bits(g.uplink_mbps.load(Ordering::Relaxed)),
+        );
+        register_gauge(
+            &meter,
+            "starlink_ping_ms",
+            "Round-trip latency to each dish's Starlink PoP, in milliseconds",
+            shared.clone(),
+            |g| f64::from_bits(g.ping_ms.load(Ordering::Relaxed)),
+        );
+        register_gauge(
+            &meter,
+            "starlink_dish_connected",
+            "Whether a dish's gRPC status stream is currently live (1) or not (0)",
+            shared,
+            |g| f64::from_bits(g.connected.load(Ordering::Relaxed)),
+        );
+
+        (MetricsRegistry { registry }, gauges)
+    }
+
+    /// Renders the current metric snapshot in Prometheus text exposition
+    /// format for the `/metrics` handler.
+    pub fn render(&self) -> String {
+        let metric_families = self.registry.gather();
+        let mut buf = Vec::new();
+        TextEncoder::new()
+            .encode(&metric_families, &mut buf)
+            .unwrap_or_default();
+        String::from_utf8(buf).unwrap_or_default()
+    }
+}
+
+fn register_gauge(
+    meter: &opentelemetry::metrics::Meter,
+    name: &'static str,
+    description: &'static str,
+    gauges: Arc<HashMap<String, DishGauges>>,
+    read: fn(&DishGauges) -> f64,
+) {
+    meter
+        .f64_observable_gauge(name)
+        .with_description(description)
+        .with_callback(move |observer| {
+            for (id, g) in gauges.iter() {
+                observer.observe(read(g), &[KeyValue::new("dish", id.clone())]);
+            }
+        })
+        .init();
+}
@@ -0,0 +1,491 @@
+//! Disk-backed history for the live dish charts.
+//!
+//! Each series (downlink, uplink, ping, obstruction) is stored as three flat files under
+//! `data_dir`: an append-only raw log at ~1 Hz, and two downsampled bucket
+//! logs (1-minute and 1-hour min/max/avg) that raw points graduate into as
+//! they age out. This keeps long time windows queryable without the raw log
+//! growing without bound.
+
+use chrono::{DateTime, Duration as ChronoDuration, TimeZone, Utc};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::Mutex;
+
+pub type DataPoint = (DateTime<Utc>, f64);
+
+/// A min/max/avg/count rollup covering one bucket of time.
+#[derive(Clone, Copy, Debug)]
+pub struct Bucket {
+    pub start: DateTime<Utc>,
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub count: u64,
+}
+
+/// Resolution requested on the `/history` route.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resolution {
+    Raw,
+    Minute,
+    Hour,
+}
+
+impl Resolution {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "raw" => Some(Resolution::Raw),
+            "minute" | "1m" => Some(Resolution::Minute),
+            "hour" | "1h" => Some(Resolution::Hour),
+            _ => None,
+        }
+    }
+}
+
+/// Append-only raw log plus downsampled bucket logs for a single series.
+pub struct SeriesStore {
+    raw_path: PathBuf,
+    minute_path: PathBuf,
+    hour_path: PathBuf,
+    /// Serializes every read/rewrite of `raw_path` between `append_raw`
+    /// (called once per second from the gRPC loop) and `roll_up`/`query`
+    /// (called from the periodic rollup task and `/history` respectively),
+    /// so a point appended mid-rollup can't be clobbered by `roll_up`'s
+    /// read-then-`fs::write` of the trimmed log.
+    raw_lock: Mutex<()>,
+    minute_buckets: Mutex<VecDeque<Bucket>>,
+    hour_buckets: Mutex<VecDeque<Bucket>>,
+}
+
+impl SeriesStore {
+    pub fn new(data_dir: &Path, series: &str) -> Self {
+        SeriesStore {
+            raw_path: data_dir.join(format!("{series}.raw.log")),
+            minute_path: data_dir.join(format!("{series}.minute.log")),
+            hour_path: data_dir.join(format!("{series}.hour.log")),
+            raw_lock: Mutex::new(()),
+            minute_buckets: Mutex::new(VecDeque::new()),
+            hour_buckets: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Loads the raw log into a fresh history buffer, capped at `capacity`
+    /// (keeping the most recent points), for pre-populating `ChartHistory`
+    /// on startup.
+    pub async fn load_raw(&self, capacity: usize) -> VecDeque<DataPoint> {
+        let _guard = self.raw_lock.lock().await;
+        let mut out = VecDeque::new();
+        for (ts, val) in read_lines(&self.raw_path).await {
+            out.push_back((ts, val));
+            if out.len() > capacity {
+                out.pop_front();
+            }
+        }
+        out
+    }
+
+    /// Loads the downsampled bucket logs into memory so `/history` queries
+    /// can serve older time windows right after startup.
+    pub async fn load_buckets(&self) {
+        let minutes: VecDeque<Bucket> = read_bucket_lines(&self.minute_path).await.into();
+        let hours: VecDeque<Bucket> = read_bucket_lines(&self.hour_path).await.into();
+        *self.minute_buckets.lock().await = minutes;
+        *self.hour_buckets.lock().await = hours;
+    }
+
+    /// Appends one new raw point to the on-disk log.
+    pub async fn append_raw(&self, point: DataPoint) -> std::io::Result<()> {
+        let _guard = self.raw_lock.lock().await;
+        append_line(
+            &self.raw_path,
+            &format!("{},{}\n", point.0.timestamp_millis(), point.1),
+        )
+        .await
+    }
+
+    /// Rolls points older than `raw_retention` out of the on-disk raw log
+    /// and into 1-minute buckets, then rolls minute buckets older than
+    /// `minute_retention` into 1-hour buckets, rewriting the bucket logs
+    /// and trimming the raw log to match. Operates on disk independently of
+    /// the in-memory `ChartHistory`, which only needs to hold the live
+    /// chart's (much shorter) display window. Called periodically, not on
+    /// every point, since it rewrites whole files.
+    pub async fn roll_up(&self, raw_retention: ChronoDuration, minute_retention: ChronoDuration) {
+        let now = Utc::now();
+        let raw_cutoff = now - raw_retention;
+
+        // Held across the read and the rewrite below so a point appended
+        // concurrently by `append_raw` either lands in `all_raw` (and is
+        // rewritten back) or is appended after `fs::write` completes —
+        // never clobbered by it.
+        let raw_guard = self.raw_lock.lock().await;
+        let all_raw = read_lines(&self.raw_path).await;
+        let split = all_raw.partition_point(|(ts, _)| *ts < raw_cutoff);
+        let (graduated, kept) = all_raw.split_at(split);
+
+        if !graduated.is_empty() {
+            let mut minute_buckets = self.minute_buckets.lock().await;
+            for bucket in bucketize(graduated, ChronoDuration::minutes(1)) {
+                merge_bucket(&mut minute_buckets, bucket);
+            }
+            rewrite_buckets(&self.minute_path, &minute_buckets).await;
+
+            let lines: String = kept
+                .iter()
+                .map(|(ts, v)| format!("{},{}\n", ts.timestamp_millis(), v))
+                .collect();
+            let _ = fs::write(&self.raw_path, lines).await;
+        }
+        drop(raw_guard);
+
+        let minute_cutoff = now - minute_retention;
+        let mut minute_buckets = self.minute_buckets.lock().await;
+        let mut graduated_minutes = Vec::new();
+        while let Some(bucket) = minute_buckets.front() {
+            if bucket.start >= minute_cutoff {
+                break;
+            }
+            graduated_minutes.push(minute_buckets.pop_front().unwrap());
+        }
+        if !graduated_minutes.is_empty() {
+            rewrite_buckets(&self.minute_path, &minute_buckets).await;
+        }
+        drop(minute_buckets);
+
+        if !graduated_minutes.is_empty() {
+            let mut hour_buckets = self.hour_buckets.lock().await;
+            for bucket in rebucketize(&graduated_minutes, ChronoDuration::hours(1)) {
+                merge_bucket(&mut hour_buckets, bucket);
+            }
+            rewrite_buckets(&self.hour_path, &hour_buckets).await;
+        }
+    }
+
+    /// Answers a `/history` query for this series at the requested
+    /// resolution. `Raw` reads straight from disk since `append_raw` flushes
+    /// every point as it arrives; `Minute` and `Hour` are served from the
+    /// in-memory bucket lists.
+    pub async fn query(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        resolution: Resolution,
+    ) -> Vec<Bucket> {
+        match resolution {
+            Resolution::Raw => {
+                let _guard = self.raw_lock.lock().await;
+                read_lines(&self.raw_path)
+                    .await
+                    .into_iter()
+                    .filter(|(ts, _)| *ts >= from && *ts <= to)
+                    .map(|(ts, v)| Bucket {
+                        start: ts,
+                        min: v,
+                        max: v,
+                        avg: v,
+                        count: 1,
+                    })
+                    .collect()
+            }
+            Resolution::Minute => self
+                .minute_buckets
+                .lock()
+                .await
+                .iter()
+                .filter(|b| b.start >= from && b.start <= to)
+                .copied()
+                .collect(),
+            Resolution::Hour => self
+                .hour_buckets
+                .lock()
+                .await
+                .iter()
+                .filter(|b| b.start >= from && b.start <= to)
+                .copied()
+                .collect(),
+        }
+    }
+}
+
+fn bucketize(points: &[DataPoint], bucket_len: ChronoDuration) -> Vec<Bucket> {
+    group_by_bucket(points.iter().map(|(ts, v)| (*ts, *v)), bucket_len)
+}
+
+fn rebucketize(buckets: &[Bucket], bucket_len: ChronoDuration) -> Vec<Bucket> {
+    // Weight each source bucket's average by its point count so coarser
+    // rollups don't silently favor sparsely-sampled buckets.
+    let expanded = buckets
+        .iter()
+        .map(|b| (b.start, b.avg, b.min, b.max, b.count));
+    let mut out: Vec<Bucket> = Vec::new();
+    for (start, avg, min, max, count) in expanded {
+        let bucket_start = floor_to(start, bucket_len);
+        if let Some(last) = out.last_mut() {
+            if last.start == bucket_start {
+                let total = last.count + count;
+                last.avg = (last.avg * last.count as f64 + avg * count as f64) / total as f64;
+                last.min = last.min.min(min);
+                last.max = last.max.max(max);
+                last.count = total;
+                continue;
+            }
+        }
+        out.push(Bucket {
+            start: bucket_start,
+            min,
+            max,
+            avg,
+            count,
+        });
+    }
+    out
+}
+
+fn group_by_bucket(
+    points: impl Iterator<Item = DataPoint>,
+    bucket_len: ChronoDuration,
+) -> Vec<Bucket> {
+    let mut out: Vec<Bucket> = Vec::new();
+    for (ts, val) in points {
+        let bucket_start = floor_to(ts, bucket_len);
+        if let Some(last) = out.last_mut() {
+            if last.start == bucket_start {
+                let total = last.count + 1;
+                last.avg = (last.avg * last.count as f64 + val) / total as f64;
+                last.min = last.min.min(val);
+                last.max = last.max.max(val);
+                last.count = total;
+                continue;
+            }
+        }
+        out.push(Bucket {
+            start: bucket_start,
+            min: val,
+            max: val,
+            avg: val,
+            count: 1,
+        });
+    }
+    out
+}
+
+fn floor_to(ts: DateTime<Utc>, bucket_len: ChronoDuration) -> DateTime<Utc> {
+    let bucket_ms = bucket_len.num_milliseconds().max(1);
+    let floored_ms = (ts.timestamp_millis() / bucket_ms) * bucket_ms;
+    Utc.timestamp_millis_opt(floored_ms).single().unwrap_or(ts)
+}
+
+fn merge_bucket(buckets: &mut VecDeque<Bucket>, bucket: Bucket) {
+    if let Some(last) = buckets.back_mut() {
+        if last.start == bucket.start {
+            let total = last.count + bucket.count;
+            last.avg =
+                (last.avg * last.count as f64 + bucket.avg * bucket.count as f64) / total as f64;
+            last.min = last.min.min(bucket.min);
+            last.max = last.max.max(bucket.max);
+            last.count = total;
+            return;
+        }
+    }
+    buckets.push_back(bucket);
+}
+
+async fn read_lines(path: &Path) -> Vec<DataPoint> {
+    let Ok(file) = fs::File::open(path).await else {
+        return Vec::new();
+    };
+    let mut lines = BufReader::new(file).lines();
+    let mut out = Vec::new();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if let Some((ts_str, val_str)) = line.split_once(',') {
+            if let (Ok(ts_ms), Ok(val)) = (ts_str.parse::<i64>(), val_str.parse::<f64>()) {
+                if let Some(ts) = Utc.timestamp_millis_opt(ts_ms).single() {
+                    out.push((ts, val));
+                }
+            }
+        }
+    }
+    out
+}
+
+async fn read_bucket_lines(path: &Path) -> Vec<Bucket> {
+    let Ok(file) = fs::File::open(path).await else {
+        return Vec::new();
+    };
+    let mut lines = BufReader::new(file).lines();
+    let mut out = Vec::new();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() != 5 {
+            continue;
+        }
+        let (Ok(start_ms), Ok(min), Ok(max), Ok(avg), Ok(count)) = (
+            fields[0].parse::<i64>(),
+            fields[1].parse::<f64>(),
+            fields[2].parse::<f64>(),
+            fields[3].parse::<f64>(),
+            fields[4].parse::<u64>(),
+        ) else {
+            continue;
+        };
+        if let Some(start) = Utc.timestamp_millis_opt(start_ms).single() {
+            out.push(Bucket {
+                start,
+                min,
+                max,
+                avg,
+                count,
+            });
+        }
+    }
+    out
+}
+
+async fn rewrite_buckets(path: &Path, buckets: &VecDeque<Bucket>) {
+    let lines: String = buckets
+        .iter()
+        .map(|b| {
+            format!(
+                "{},{},{},{},{}\n",
+                b.start.timestamp_millis(),
+                b.min,
+                b.max,
+                b.avg,
+                b.count
+            )
+        })
+        .collect();
+    let _ = fs::write(path, lines).await;
+}
+
+async fn append_line(path: &Path, line: &str) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(line.as_bytes()).await?;
+    file.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(secs, 0).single().unwrap()
+    }
+
+    #[test]
+    fn floor_to_rounds_down_to_bucket_boundary() {
+        let bucket = ChronoDuration::minutes(1);
+        assert_eq!(floor_to(ts(90), bucket), ts(60));
+        assert_eq!(floor_to(ts(60), bucket), ts(60));
+        assert_eq!(floor_to(ts(0), bucket), ts(0));
+    }
+
+    #[test]
+    fn group_by_bucket_averages_points_in_the_same_bucket() {
+        let points = vec![(ts(0), 1.0), (ts(10), 3.0), (ts(61), 10.0)];
+        let buckets = group_by_bucket(points.into_iter(), ChronoDuration::minutes(1));
+
+        assert_eq!(buckets.len(), 2);
+        assert_eq!(buckets[0].start, ts(0));
+        assert_eq!(buckets[0].min, 1.0);
+        assert_eq!(buckets[0].max, 3.0);
+        assert_eq!(buckets[0].avg, 2.0);
+        assert_eq!(buckets[0].count, 2);
+        assert_eq!(buckets[1].start, ts(60));
+        assert_eq!(buckets[1].count, 1);
+    }
+
+    #[test]
+    fn rebucketize_weights_by_source_count() {
+        let buckets = vec![
+            Bucket {
+                start: ts(0),
+                min: 0.0,
+                max: 2.0,
+                avg: 1.0,
+                count: 3,
+            },
+            Bucket {
+                start: ts(30),
+                min: 4.0,
+                max: 4.0,
+                avg: 4.0,
+                count: 1,
+            },
+        ];
+        let rebucketed = rebucketize(&buckets, ChronoDuration::minutes(1));
+
+        assert_eq!(rebucketed.len(), 1);
+        assert_eq!(rebucketed[0].count, 4);
+        assert_eq!(rebucketed[0].min, 0.0);
+        assert_eq!(rebucketed[0].max, 4.0);
+        assert_eq!(rebucketed[0].avg, (1.0 * 3.0 + 4.0 * 1.0) / 4.0);
+    }
+
+    #[test]
+    fn merge_bucket_combines_into_last_bucket_with_same_start() {
+        let mut buckets = VecDeque::new();
+        merge_bucket(
+            &mut buckets,
+            Bucket {
+                start: ts(0),
+                min: 1.0,
+                max: 1.0,
+                avg: 1.0,
+                count: 1,
+            },
+        );
+        merge_bucket(
+            &mut buckets,
+            Bucket {
+                start: ts(0),
+                min: 3.0,
+                max: 3.0,
+                avg: 3.0,
+                count: 1,
+            },
+        );
+
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].min, 1.0);
+        assert_eq!(buckets[0].max, 3.0);
+        assert_eq!(buckets[0].avg, 2.0);
+        assert_eq!(buckets[0].count, 2);
+    }
+
+    #[test]
+    fn merge_bucket_appends_when_start_differs() {
+        let mut buckets = VecDeque::new();
+        merge_bucket(
+            &mut buckets,
+            Bucket {
+                start: ts(0),
+                min: 1.0,
+                max: 1.0,
+                avg: 1.0,
+                count: 1,
+            },
+        );
+        merge_bucket(
+            &mut buckets,
+            Bucket {
+                start: ts(60),
+                min: 2.0,
+                max: 2.0,
+                avg: 2.0,
+                count: 1,
+            },
+        );
+
+        assert_eq!(buckets.len(), 2);
+    }
+}